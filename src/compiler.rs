@@ -73,66 +73,294 @@ pub enum Inst {
     /// ```
     Jnz,
 
+    /// Add an immediate signed delta to the byte at the data pointer (mod 256).
+    ///
+    /// This is a special instruction, as it is encoded with a single `i8` operand byte after it.
+    ///
+    /// This is what a run of `+`/`-` folds into, so `++++` is one `Add(4)` instead of four `Inc`s.
+    Add,
+
+    /// Move the data pointer by an immediate signed offset.
+    ///
+    /// This is a special instruction, as it is encoded with a [`ADDR_SIZE`] byte `i32` operand (LE)
+    /// after it, just like `Jz`/`Jnz`.
+    ///
+    /// This is what a run of `>`/`<` folds into, so `>>>` is one `Move(3)` instead of three `IncPtr`s.
+    Move,
+
+    /// Set the byte at the data pointer to zero.
+    ///
+    /// This is what the `[-]`/`[+]` clear-loop idiom folds into, no looping required at runtime.
+    SetZero,
+
+    /// Multiply-add: `data[ptr + offset] += factor * data[ptr]` (mod 256).
+    ///
+    /// This is a special instruction, as it is encoded with a [`ADDR_SIZE`] byte `i32` offset (LE)
+    /// followed by a single `i8` factor byte.
+    ///
+    /// This is what the body of a balanced copy/multiply loop folds into: a sequence of these (one
+    /// per touched offset) followed by a [`SetZero`] for the home cell, so a loop that copies or
+    /// multiplies runs without looping at runtime.
+    MulAdd,
+
     /// It's time to stop, ok?
     Halt,
 }
 
+/// An instruction in the optimizer's intermediate representation.
+///
+/// Unlike [`Inst`], these carry their operands inline, which is what makes the optimizing pass
+/// (run after lexing, before emission) convenient to write. They are lowered to byte-code by
+/// [`emit`].
+enum Op {
+    /// Net signed delta of a run of `+`/`-` (mod 256).
+    Add(i8),
+    /// Net signed offset of a run of `>`/`<`.
+    Move(i32),
+    /// `.`
+    Out,
+    /// `,`
+    Inp,
+    /// The `[-]`/`[+]` clear idiom.
+    SetZero,
+    /// `data[ptr + offset] += factor * data[ptr]`, part of a folded copy/multiply loop.
+    MulAdd { offset: i32, factor: i8 },
+    /// A loop that survived optimization, to be emitted as `Jz`/`Jnz`.
+    Loop(Vec<Op>),
+}
+
 /// Converts lexer output into byte-code.
 ///
 /// Returns `Err` if `[`s and `]`s are not matched properly in the source.
+///
+/// This happens in three steps: the source is first lexed and lowered into an [`Op`] IR (folding
+/// `+`/`-` and `>`/`<` runs along the way), then an optimizing pass recognizes clear/copy/multiply
+/// loop idioms, and finally the IR is [`emit`]ted as byte-code.
 pub fn compile(mut source: Lexer) -> Result<Vec<u8>, ()> {
-    let mut v = Vec::with_capacity(source.len_hint());
-    let mut jump_stack = Vec::with_capacity(4);
+    // Rough guess, one byte-code byte per source byte; the vec grows in `emit` as needed and is
+    // shrunk at the end.
+    let cap = source.len_hint();
+
+    let ops = optimize(lower(&mut source, false)?);
+
+    let mut v = Vec::with_capacity(cap);
+    emit(&ops, &mut v)?;
+
+    // Add a halt instruction to the end of the program, this maybe probably helps
+    // avoid the bounds checks.
+    v.push(Inst::Halt.to_bc());
+
+    // Shrink the byte code vec, because I feel like it.
+    v.shrink_to_fit();
+
+    // Compilation succeeded :thumbeline:
+    Ok(v)
+}
+
+/// Lowers the lexer output into the [`Op`] IR, folding `+`/`-` and `>`/`<` runs as it goes.
+///
+/// `in_loop` is `true` while lowering the body of a `[`, so that the matching `]` ends the current
+/// sequence (and a premature [`Eof`] is an error). The top-level call passes `false`.
+///
+/// Returns `Err` if `[`s and `]`s are not matched properly.
+fn lower(source: &mut Lexer, in_loop: bool) -> Result<Vec<Op>, ()> {
+    use crate::lex::Token::*;
+
+    let mut ops = Vec::new();
 
     loop {
-        use crate::lex::Token::*;
+        match source.next() {
+            // Fold runs of pointer moves / cell updates into a single operand-carrying op.
+            RAngle => push_move(&mut ops, 1),
+            LAngle => push_move(&mut ops, -1),
 
-        let t = source.next();
+            Plus => push_add(&mut ops, 1),
+            Minus => push_add(&mut ops, -1),
 
-        match t {
-            // Simple instructions that just record their representation
-            RAngle => v.push(Inst::IncPtr.to_bc()),
-            LAngle => v.push(Inst::DecPtr.to_bc()),
+            Dot => ops.push(Op::Out),
+            Comma => ops.push(Op::Inp),
 
-            Plus => v.push(Inst::Inc.to_bc()),
-            Minus => v.push(Inst::Dec.to_bc()),
+            // Recurse into the loop body; `?` propagates unmatched-bracket errors.
+            LBrack => ops.push(Op::Loop(lower(source, true)?)),
 
-            Dot => v.push(Inst::Out.to_bc()),
-            Comma => v.push(Inst::Inp.to_bc()),
+            // A matching `]` ends this (nested) sequence...
+            RBrack if in_loop => break Ok(ops),
+            // ...but a `]` with no matching `[` is malformed.
+            RBrack => break Err(()),
 
-            // More 'fun' stuff
-            LBrack => {
-                // Record the jump if zero instruction itself
-                v.push(Inst::Jz.to_bc());
+            Comment => continue,
 
-                // Record the address where to jump; since we don't yet know where the matching `]`
-                // is, we just push a temporary garbage.
-                v.extend([42; ADDR_SIZE]);
+            // The program has ended in the middle of a loop, so some `[` is unmatched.
+            Eof if in_loop => break Err(()),
 
-                // Push the addr where the matching `]` should jump to (after the addr encoding):
-                //
-                // [... Jz, 42, 42, 42, 42, ø]
-                //                          ^-- this is what we store
-                //                              in the jump stack
-                jump_stack.push(v.len());
+            Eof => break Ok(ops),
+        }
+    }
+}
+
+/// Folds a `+1`/`-1` cell delta into the trailing [`Op::Add`], or pushes a fresh one.
+///
+/// Deltas that cancel out to zero are dropped entirely.
+fn push_add(ops: &mut Vec<Op>, delta: i8) {
+    match ops.last_mut() {
+        Some(Op::Add(d)) => {
+            *d = d.wrapping_add(delta);
+            if *d == 0 {
+                ops.pop();
             }
+        }
+        _ => ops.push(Op::Add(delta)),
+    }
+}
 
-            RBrack => {
-                // Record the jump if non zero instruction itself
-                v.push(Inst::Jnz.to_bc());
+/// Folds a `+1`/`-1` pointer offset into the trailing [`Op::Move`], or pushes a fresh one.
+///
+/// Offsets that cancel out to zero are dropped entirely.
+fn push_move(ops: &mut Vec<Op>, offset: i32) {
+    match ops.last_mut() {
+        Some(Op::Move(o)) => {
+            *o += offset;
+            if *o == 0 {
+                ops.pop();
+            }
+        }
+        _ => ops.push(Op::Move(offset)),
+    }
+}
+
+/// Runs the peephole pass over a sequence, recursing into (and possibly dissolving) loops.
+fn optimize(ops: Vec<Op>) -> Vec<Op> {
+    let mut out = Vec::with_capacity(ops.len());
+
+    for op in ops {
+        match op {
+            Op::Loop(body) => {
+                // Optimize the body first, so a loop whose body became non-trivial (e.g. it
+                // contains a folded inner loop) is correctly left alone by `recognize`.
+                let body = optimize(body);
+
+                match recognize(&body) {
+                    Some(folded) => out.extend(folded),
+                    None => out.push(Op::Loop(body)),
+                }
+            }
+            other => out.push(other),
+        }
+    }
+
+    out
+}
+
+/// Tries to recognize a loop `body` as a clear/copy/multiply idiom and fold it into straight-line
+/// ops.
+///
+/// Returns `None` (leaving the loop as-is) unless the body consists *only* of cell updates and
+/// pointer moves (`+ - < >`) with zero net pointer movement:
+/// - `[-]`/`[+]` (only the home cell touched, by ±1) becomes a single [`Op::SetZero`];
+/// - a balanced loop whose home cell is decremented by exactly 1 per iteration becomes one
+///   [`Op::MulAdd`] per other touched offset followed by an [`Op::SetZero`].
+///
+/// Anything else (nonzero net movement, a home delta that isn't `-1`, I/O or nested loops in the
+/// body) is left for the interpreter to run as an actual loop.
+fn recognize(body: &[Op]) -> Option<Vec<Op>> {
+    // Net delta per offset, in first-touched order (so the emitted `MulAdd`s are deterministic).
+    let mut deltas: Vec<(i32, i8)> = Vec::new();
+    let mut offset: i32 = 0;
+
+    for op in body {
+        match op {
+            Op::Add(delta) => match deltas.iter_mut().find(|(o, _)| *o == offset) {
+                Some((_, d)) => *d = d.wrapping_add(*delta),
+                None => deltas.push((offset, *delta)),
+            },
+            Op::Move(m) => offset += m,
+            // Output, input, already-folded idioms or nested loops: not a foldable loop.
+            _ => return None,
+        }
+    }
+
+    // A foldable loop must return the pointer to where it started.
+    if offset != 0 {
+        return None;
+    }
+
+    let home = deltas
+        .iter()
+        .find(|(o, _)| *o == 0)
+        .map(|&(_, d)| d)
+        .unwrap_or(0);
+
+    // `[-]` / `[+]`: only the home cell is touched, by ±1, so the loop always ends with it at zero.
+    if deltas.iter().all(|&(o, _)| o == 0) && (home == -1 || home == 1) {
+        return Some(vec![Op::SetZero]);
+    }
+
+    // Copy/multiply loops are only correct to unroll when the home cell is decremented by exactly
+    // 1 per iteration (so it runs `data[ptr]` times and ends at zero).
+    if home != -1 {
+        return None;
+    }
+
+    let mut folded = Vec::with_capacity(deltas.len());
+    for &(off, factor) in &deltas {
+        if off != 0 {
+            folded.push(Op::MulAdd {
+                offset: off,
+                factor,
+            });
+        }
+    }
+    folded.push(Op::SetZero);
+
+    Some(folded)
+}
+
+/// Lowers the optimized [`Op`] IR into byte-code, appending to `v`.
+///
+/// Surviving loops are emitted as `Jz`/`Jnz` with back-patched addresses, exactly as the `[`/`]`
+/// desugaring on [`Inst::Jz`] describes.
+///
+/// Returns `Err` if a jump address does not fit into [`Addr`].
+fn emit(ops: &[Op], v: &mut Vec<u8>) -> Result<(), ()> {
+    for op in ops {
+        match *op {
+            Op::Add(delta) => {
+                v.push(Inst::Add.to_bc());
+                v.push(delta as u8);
+            }
+            Op::Move(offset) => {
+                v.push(Inst::Move.to_bc());
+                v.extend(offset.to_le_bytes());
+            }
+            Op::Out => v.push(Inst::Out.to_bc()),
+            Op::Inp => v.push(Inst::Inp.to_bc()),
+            Op::SetZero => v.push(Inst::SetZero.to_bc()),
+            Op::MulAdd { offset, factor } => {
+                v.push(Inst::MulAdd.to_bc());
+                v.extend(offset.to_le_bytes());
+                v.push(factor as u8);
+            }
+            Op::Loop(ref body) => {
+                // Record the jump if zero instruction itself, followed by temporary garbage for
+                // the (not yet known) address of the matching `]`.
+                v.push(Inst::Jz.to_bc());
+                v.extend([42; ADDR_SIZE]);
 
-                // Find the matching `[`
-                let Some(jump_addr) = jump_stack.pop() else { break Err(()) };
+                // The `]` jumps back here, to the first instruction of the body.
+                let body_start = v.len();
 
-                // Record the address where this `]` will jump to
-                let jump_there = Addr::try_from(jump_addr).map_err(drop)?.to_le_bytes();
-                v.extend(jump_there);
+                emit(body, v)?;
 
-                // Patch the address where the matching `[` jumps to.
-                // Note that we use `jump_addr - ADDR_SIZE`,
-                // because `jump_addr` is *after* the address.
-                let jump_here = Addr::try_from(v.len()).map_err(drop)?.to_le_bytes();
-                v[jump_addr - ADDR_SIZE..][..ADDR_SIZE].copy_from_slice(&jump_here);
+                // Record the jump if non zero instruction and the address it jumps back to.
+                v.push(Inst::Jnz.to_bc());
+                let jump_back = Addr::try_from(body_start).map_err(drop)?.to_le_bytes();
+                v.extend(jump_back);
+
+                // Patch the address where the matching `[` jumps to (past the whole loop).
+                // Note that we use `body_start - ADDR_SIZE`, because `body_start` is *after* the
+                // address.
+                let jump_past = Addr::try_from(v.len()).map_err(drop)?.to_le_bytes();
+                v[body_start - ADDR_SIZE..][..ADDR_SIZE].copy_from_slice(&jump_past);
 
                 // After this we have the following picture:
                 //
@@ -149,28 +377,10 @@ pub fn compile(mut source: Lexer) -> Result<Vec<u8>, ()> {
                 // N is the instruction following `Jz`.
                 // (it could possibly be Jnz, which would make a `if data is not 0 { loop{} }`)
             }
-
-            Comment => continue,
-
-            // The program has ended, but we still haven't found a match for some `[`,
-            // this is not a valid BF program.
-            Eof if !jump_stack.is_empty() => {
-                break Err(());
-            }
-
-            Eof => {
-                // Add a halt instruction to the end of the program, this maybe probably helps
-                // avoid the bounds checks.
-                v.push(Inst::Halt.to_bc());
-
-                // Shrink the byte code vec, because I feel like it.
-                v.shrink_to_fit();
-
-                // Compilation succeeded :thumbeline:
-                break Ok(v);
-            }
         }
     }
+
+    Ok(())
 }
 
 impl Inst {
@@ -197,6 +407,10 @@ impl Inst {
         const Inp: u8 = Inst::Inp.to_bc();
         const Jf: u8 = Inst::Jz.to_bc();
         const Jb: u8 = Inst::Jnz.to_bc();
+        const Add: u8 = Inst::Add.to_bc();
+        const Move: u8 = Inst::Move.to_bc();
+        const SetZero: u8 = Inst::SetZero.to_bc();
+        const MulAdd: u8 = Inst::MulAdd.to_bc();
         const Halt: u8 = Inst::Halt.to_bc();
 
         let inst = match x {
@@ -208,6 +422,10 @@ impl Inst {
             Inp => Inst::Inp,
             Jf => Inst::Jz,
             Jb => Inst::Jnz,
+            Add => Inst::Add,
+            Move => Inst::Move,
+            SetZero => Inst::SetZero,
+            MulAdd => Inst::MulAdd,
             Halt => Inst::Halt,
             _ => return None,
         };
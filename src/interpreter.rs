@@ -47,8 +47,8 @@ impl<'bc, 'io> Interpreter<'bc, 'io> {
         self.dispatch();
     }
 
-    const DISPATCH_TABLE: [fn(&mut Interpreter<'bc, 'io>); 9] = {
-        let mut tmp: [fn(&mut Interpreter<'bc, 'io>); 9] = [|_| (); 9];
+    const DISPATCH_TABLE: [fn(&mut Interpreter<'bc, 'io>); 13] = {
+        let mut tmp: [fn(&mut Interpreter<'bc, 'io>); 13] = [|_| (); 13];
 
         // Use indexing here, instead of just creating array with function already in place,
         // so that his fails compilation in case `Inst`'s discriminants change,
@@ -61,6 +61,10 @@ impl<'bc, 'io> Interpreter<'bc, 'io> {
         tmp[Inp as usize] = Interpreter::inp;
         tmp[Jz as usize] = Interpreter::jz;
         tmp[Jnz as usize] = Interpreter::jnz;
+        tmp[Add as usize] = Interpreter::add;
+        tmp[Move as usize] = Interpreter::mov;
+        tmp[SetZero as usize] = Interpreter::set_zero;
+        tmp[MulAdd as usize] = Interpreter::mul_add;
         tmp[Halt as usize] = Interpreter::halt;
 
         tmp
@@ -171,6 +175,69 @@ impl<'bc, 'io> Interpreter<'bc, 'io> {
         }
     }
 
+    /// Handle the [`Add`] instruction.
+    fn add(&mut self) {
+        debug_assert!(self.at(Add));
+
+        let delta = self.bc[self.cursor + 1] as i8;
+
+        let byte = self.deref_mut();
+        *byte = byte.wrapping_add(delta as u8);
+
+        // account for the operand byte
+        self.cursor += 1;
+        become self.next();
+    }
+
+    /// Handle the [`Move`] instruction.
+    fn mov(&mut self) {
+        debug_assert!(self.at(Move));
+
+        let offset =
+            i32::from_le_bytes(self.bc[self.cursor..][1..][..ADDR_SIZE].try_into().unwrap());
+
+        // FIXME: bound check
+        self.ptr = (self.ptr as isize).wrapping_add(offset as isize) as usize;
+
+        // account for the operand encoding
+        self.cursor += ADDR_SIZE;
+        become self.next();
+    }
+
+    /// Handle the [`SetZero`] instruction.
+    fn set_zero(&mut self) {
+        debug_assert!(self.at(SetZero));
+
+        *self.deref_mut() = 0;
+        become self.next();
+    }
+
+    /// Handle the [`MulAdd`] instruction.
+    fn mul_add(&mut self) {
+        debug_assert!(self.at(MulAdd));
+
+        let offset =
+            i32::from_le_bytes(self.bc[self.cursor..][1..][..ADDR_SIZE].try_into().unwrap());
+        let factor = self.bc[self.cursor + 1 + ADDR_SIZE] as i8;
+
+        // Read the home cell (the loop counter) *before* it gets cleared.
+        let home = self.deref();
+
+        // FIXME: bound check
+        let target = (self.ptr as isize).wrapping_add(offset as isize) as usize;
+        if self.data.len() <= target {
+            self.data.resize(target + 1, 0);
+        }
+
+        // `factor * home` mod 256; `factor as u8` keeps the product well-defined on wrap-around.
+        let product = (factor as u8).wrapping_mul(home);
+        self.data[target] = self.data[target].wrapping_add(product);
+
+        // account for the operand encoding (offset + factor)
+        self.cursor += ADDR_SIZE + 1;
+        become self.next();
+    }
+
     /// Handle the [`Halt`] instruction.
     fn halt(&mut self) {
         debug_assert!(self.at(Halt));
@@ -233,6 +300,26 @@ impl<'bc, 'io> Interpreter<'bc, 'io> {
                     );
                     i += 4
                 }
+                Add => {
+                    print!("add({})", self.bc[i + 1] as i8);
+                    i += 1
+                }
+                Move => {
+                    print!(
+                        "mov({})",
+                        i32::from_le_bytes(self.bc[i..][1..][..4].try_into().unwrap())
+                    );
+                    i += 4
+                }
+                SetZero => print!("clr"),
+                MulAdd => {
+                    print!(
+                        "mul({}, {})",
+                        i32::from_le_bytes(self.bc[i..][1..][..4].try_into().unwrap()),
+                        self.bc[i + 5] as i8
+                    );
+                    i += 5
+                }
                 Halt => print!("(halt)"),
             }
             i += 1;
@@ -61,3 +61,169 @@ fn interpret(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::{compile, Inst, ADDR_SIZE};
+    use crate::interpreter::Interpreter;
+    use crate::lex::Lexer;
+
+    /// Compile `src` with the optimizer and run it through the [`Interpreter`], returning the bytes
+    /// it wrote to output.
+    fn run(src: &str, input: &[u8]) -> Vec<u8> {
+        let bc = compile(Lexer(src)).unwrap();
+
+        let mut input = input.iter().copied();
+        let mut out = Vec::new();
+        Interpreter(
+            &bc,
+            &mut || input.next().unwrap_or(0),
+            &mut |c| out.push(c),
+        )
+        .run();
+
+        out
+    }
+
+    /// A naive, un-optimized reference interpreter operating straight on the source text, used as
+    /// the known-good oracle the optimized pipeline is compared against.
+    fn run_naive(src: &str, input: &[u8]) -> Vec<u8> {
+        let prog: Vec<u8> = src.bytes().filter(|c| b"><+-.,[]".contains(c)).collect();
+
+        let mut data = vec![0u8];
+        let mut ptr = 0usize;
+        let mut pc = 0usize;
+        let mut input = input.iter().copied();
+        let mut out = Vec::new();
+
+        while pc < prog.len() {
+            match prog[pc] {
+                b'>' => {
+                    ptr += 1;
+                    if ptr >= data.len() {
+                        data.resize(ptr + 1, 0);
+                    }
+                }
+                b'<' => ptr -= 1,
+                b'+' => data[ptr] = data[ptr].wrapping_add(1),
+                b'-' => data[ptr] = data[ptr].wrapping_sub(1),
+                b'.' => out.push(data[ptr]),
+                b',' => data[ptr] = input.next().unwrap_or(0),
+                b'[' if data[ptr] == 0 => {
+                    let mut depth = 1;
+                    while depth != 0 {
+                        pc += 1;
+                        match prog[pc] {
+                            b'[' => depth += 1,
+                            b']' => depth -= 1,
+                            _ => {}
+                        }
+                    }
+                }
+                b']' if data[ptr] != 0 => {
+                    let mut depth = 1;
+                    while depth != 0 {
+                        pc -= 1;
+                        match prog[pc] {
+                            b']' => depth += 1,
+                            b'[' => depth -= 1,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+            pc += 1;
+        }
+
+        out
+    }
+
+    /// Decode a byte-code stream back into the sequence of opcodes it contains, skipping the inline
+    /// operands of the wide instructions, so tests can assert on which idioms the optimizer folded.
+    fn opcodes(src: &str) -> Vec<Inst> {
+        use Inst::*;
+
+        let bc = compile(Lexer(src)).unwrap();
+
+        let mut insts = Vec::new();
+        let mut i = 0;
+        while i < bc.len() {
+            let inst = Inst::from_bc(bc[i]).unwrap();
+            insts.push(inst);
+            i += 1 + match inst {
+                Jz | Jnz | Move => ADDR_SIZE,
+                Add => 1,
+                MulAdd => ADDR_SIZE + 1,
+                _ => 0,
+            };
+        }
+        insts
+    }
+
+    /// The optimized output must match the naive reference on every standard program.
+    #[test]
+    fn optimized_matches_reference() {
+        const HELLO_WORLD: &str = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]\
+            >>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+
+        let programs = [
+            "++++[-].",          // clear idiom
+            "+++++[->+<]>.",     // copy loop
+            "+++[->++<]>.",      // multiply loop
+            "++[->+>+<<]>.>.",   // multi-offset copy loop
+            "++++[--].",         // even counter, decremented by 2: a loop, not a clear
+            "++>+++<[>]",        // nonzero net movement: must stay a loop
+            ",+.",               // touches input
+            HELLO_WORLD,
+        ];
+
+        for src in programs {
+            assert_eq!(
+                run(src, &[7]),
+                run_naive(src, &[7]),
+                "optimized output diverged from reference on {src:?}",
+            );
+        }
+    }
+
+    /// The clear and copy/multiply idioms must actually fold away their loop, while the loops that
+    /// don't match the idiom must keep their `Jz`/`Jnz`.
+    #[test]
+    fn idioms_fold_and_non_idioms_dont() {
+        use Inst::*;
+
+        // `[-]` collapses to a single `SetZero`, no branch left.
+        let clear = opcodes("++++[-]");
+        assert!(clear.contains(&SetZero));
+        assert!(!clear.contains(&Jz));
+
+        // `[->+<]` collapses to `MulAdd` + `SetZero`, again branchless.
+        let copy = opcodes("+++++[->+<]");
+        assert!(copy.contains(&MulAdd));
+        assert!(copy.contains(&SetZero));
+        assert!(!copy.contains(&Jz));
+
+        // A decrement-by-two loop is not a clear idiom and must survive as a real loop.
+        let twos = opcodes("++++[--]");
+        assert!(twos.contains(&Jz));
+        assert!(!twos.contains(&SetZero));
+        assert!(!twos.contains(&MulAdd));
+
+        // Nonzero net pointer movement disqualifies folding.
+        let scan = opcodes("++>+++<[>]");
+        assert!(scan.contains(&Jz));
+        assert!(!scan.contains(&MulAdd));
+    }
+
+    /// A source that optimizes down to nothing still yields a lone `Halt`, and the interpreter stops
+    /// cleanly on it without producing any output.
+    #[test]
+    fn empty_program_is_just_halt() {
+        for src in ["", "comment only no real ops", "+-"] {
+            let bc = compile(Lexer(src)).unwrap();
+            assert_eq!(bc, vec![Inst::Halt.to_bc()], "on {src:?}");
+            assert_eq!(run(src, &[]), Vec::<u8>::new(), "on {src:?}");
+        }
+    }
+}